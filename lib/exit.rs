@@ -0,0 +1,22 @@
+use std::{cell::Cell, process::ExitCode, rc::Rc};
+
+/**
+    Shared, cloneable exit code storage for a running [`Runtime`](crate::Runtime).
+
+    Stored in [`Lua`](mlua::Lua) app data so that [`LuaRuntimeExt::set_exit_code`]
+    can be called from anywhere a [`Lua`](mlua::Lua) reference is available.
+
+    [`LuaRuntimeExt::set_exit_code`]: crate::LuaRuntimeExt::set_exit_code
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Exit(Rc<Cell<Option<ExitCode>>>);
+
+impl Exit {
+    pub(crate) fn set(&self, code: ExitCode) {
+        self.0.set(Some(code));
+    }
+
+    pub(crate) fn get(&self) -> Option<ExitCode> {
+        self.0.get()
+    }
+}
@@ -0,0 +1,16 @@
+/*!
+    A small, `!Send`-friendly runtime and scheduler for running Luau code on top
+    of mlua, pairing an [`async_executor::Executor`] with queues of spawned and
+    deferred lua threads.
+*/
+
+mod exit;
+mod queue;
+mod result_map;
+mod runtime;
+mod thread_id;
+mod traits;
+
+pub use runtime::Runtime;
+pub use thread_id::ThreadId;
+pub use traits::{IntoLuaThread, LocalTask, LuaRuntimeExt, LuaSpawnExt};
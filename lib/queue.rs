@@ -0,0 +1,183 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use mlua::prelude::*;
+
+use crate::thread_id::ThreadId;
+
+/**
+    A lua thread that has been pushed onto a [`Runtime`](crate::Runtime), waiting to be resumed.
+
+    Threads and their arguments are stored using [`LuaRegistryKey`]s so that the queue itself
+    does not need to be generic over the lua `'lua` lifetime.
+*/
+pub(crate) struct QueuedThread {
+    pub(crate) id: ThreadId,
+    pub(crate) key: LuaRegistryKey,
+    pub(crate) args: Vec<LuaRegistryKey>,
+}
+
+struct ThreadQueueInner(RefCell<VecDeque<QueuedThread>>);
+
+macro_rules! thread_queue {
+    ($name:ident) => {
+        #[derive(Clone)]
+        pub struct $name(Rc<ThreadQueueInner>);
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self(Rc::new(ThreadQueueInner(RefCell::new(VecDeque::new()))))
+            }
+        }
+
+        impl $name {
+            /**
+                Pushes a new item onto the queue, returning the [`ThreadId`] it was assigned.
+
+                # Errors
+
+                Errors when out of memory, or when the thread or any of its arguments
+                cannot be stored in the lua registry.
+            */
+            pub fn push_item<'lua>(
+                &self,
+                lua: &'lua Lua,
+                thread: impl crate::traits::IntoLuaThread<'lua>,
+                args: impl IntoLuaMulti<'lua>,
+            ) -> LuaResult<ThreadId> {
+                let thread = thread.into_lua_thread(lua)?;
+                let args = args.into_lua_multi(lua)?;
+
+                let id = ThreadId::next();
+                let key = lua.create_registry_value(thread)?;
+                let args = args
+                    .into_iter()
+                    .map(|value| lua.create_registry_value(value))
+                    .collect::<LuaResult<Vec<_>>>()?;
+
+                self.0
+                    .0
+                    .borrow_mut()
+                    .push_back(QueuedThread { id, key, args });
+
+                Ok(id)
+            }
+
+            /**
+                Pops the next queued item off of the front of the queue, if any.
+            */
+            pub(crate) fn pop_item(&self) -> Option<QueuedThread> {
+                self.0 .0.borrow_mut().pop_front()
+            }
+
+            /**
+                Pushes an already-registered thread back onto the queue, preserving
+                its original [`ThreadId`] - used to resume a thread that has yielded
+                rather than finished.
+            */
+            pub(crate) fn push_requeue(&self, queued: QueuedThread) {
+                self.0 .0.borrow_mut().push_back(queued);
+            }
+
+            /**
+                Cancels a queued item, removing it from the queue before it has
+                had a chance to start running.
+
+                Returns `true` if an item with the given [`ThreadId`] was found
+                and removed, `false` otherwise.
+            */
+            pub fn cancel_item(&self, id: ThreadId) -> bool {
+                let mut queue = self.0 .0.borrow_mut();
+                let Some(pos) = queue.iter().position(|queued| queued.id == id) else {
+                    return false;
+                };
+                queue.remove(pos);
+                true
+            }
+
+            pub(crate) fn is_empty(&self) -> bool {
+                self.0 .0.borrow().is_empty()
+            }
+        }
+    };
+}
+
+thread_queue!(SpawnedThreadQueue);
+thread_queue!(DeferredThreadQueue);
+
+/**
+    A queue of threads that were resumed and found still resumable this tick.
+
+    Kept separate from [`SpawnedThreadQueue`] so that [`Runtime::run`](crate::Runtime::run)
+    can merge it back in at the start of the next tick instead of a thread that
+    keeps yielding being re-popped and re-resumed within the same tick, which
+    would starve every other spawned thread, task, and the executor itself.
+*/
+thread_queue!(RequeuedThreadQueue);
+
+struct QueuedFuture {
+    cancelled: Rc<Cell<bool>>,
+    fut: Pin<Box<dyn Future<Output = ()>>>,
+}
+
+/**
+    A queue of thread-local (`!Send`) futures spawned with [`LuaSpawnExt::spawn_local`].
+
+    [`LuaSpawnExt::spawn_local`]: crate::LuaSpawnExt::spawn_local
+*/
+#[derive(Clone, Default)]
+pub struct FuturesQueue(Rc<RefCell<Vec<QueuedFuture>>>);
+
+impl FuturesQueue {
+    /**
+        Pushes a new thread-local future onto the queue.
+
+        Returns a shared cancellation flag - setting it to `true` causes the
+        future to be dropped, without being polled again, on the next
+        [`drain_polls`](FuturesQueue::drain_polls).
+    */
+    pub(crate) fn push_item(&self, fut: impl Future<Output = ()> + 'static) -> Rc<Cell<bool>> {
+        let cancelled = Rc::new(Cell::new(false));
+        self.0.borrow_mut().push(QueuedFuture {
+            cancelled: Rc::clone(&cancelled),
+            fut: Box::pin(fut),
+        });
+        cancelled
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    /**
+        Polls every queued future once, dropping any that have completed or
+        been cancelled.
+
+        Returns `true` if any future completed during this drain, which the
+        runtime uses as a signal that it made progress this tick.
+    */
+    pub(crate) fn drain_polls(&self, cx: &mut Context) -> bool {
+        let mut made_progress = false;
+
+        self.0.borrow_mut().retain_mut(|queued| {
+            if queued.cancelled.get() {
+                return false;
+            }
+            match queued.fut.as_mut().poll(cx) {
+                Poll::Ready(()) => {
+                    made_progress = true;
+                    false
+                }
+                Poll::Pending => true,
+            }
+        });
+
+        made_progress
+    }
+}
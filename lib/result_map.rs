@@ -0,0 +1,249 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+};
+
+use event_listener::Event;
+use futures_lite::stream::{self, Stream};
+use mlua::prelude::*;
+
+use crate::thread_id::ThreadId;
+
+/**
+    The outcome of a finished lua thread, stored using [`LuaRegistryKey`]s so that
+    it does not need to be generic over the lua `'lua` lifetime.
+*/
+pub(crate) struct ThreadResult(LuaResult<Vec<LuaRegistryKey>>);
+
+impl ThreadResult {
+    pub(crate) fn cancelled() -> Self {
+        Self(Err(LuaError::RuntimeError(
+            "thread was cancelled".to_string(),
+        )))
+    }
+
+    pub(crate) fn timed_out() -> Self {
+        Self(Err(LuaError::RuntimeError(
+            "thread timed out".to_string(),
+        )))
+    }
+
+    /**
+        Converts this result back into a [`LuaMultiValue`], using the given [`Lua`]
+        instance to dereference the stored [`LuaRegistryKey`]s.
+
+        # Errors
+
+        Errors if the thread errored, or if a stored value can no longer be
+        found in the lua registry.
+    */
+    pub(crate) fn value(self, lua: &Lua) -> LuaResult<LuaMultiValue> {
+        let keys = self.0?;
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            values.push(lua.registry_value(&key)?);
+            lua.remove_registry_value(key).ok();
+        }
+        Ok(LuaMultiValue::from_vec(values))
+    }
+}
+
+#[derive(Default)]
+struct ThreadSlot {
+    result: Option<ThreadResult>,
+    yields: VecDeque<LuaResult<Vec<LuaRegistryKey>>>,
+}
+
+/**
+    A map of [`ThreadId`]s to the (possibly not yet available) results of their
+    corresponding lua threads.
+
+    Threads must be registered with [`track`](ThreadResultMap::track) before their
+    status, intermediate yields, or final result can be observed.
+*/
+#[derive(Clone, Default)]
+pub struct ThreadResultMap {
+    slots: Rc<RefCell<HashMap<ThreadId, ThreadSlot>>>,
+    event: Rc<Event>,
+}
+
+impl ThreadResultMap {
+    /**
+        Starts tracking the given thread, so that its result may later be observed.
+    */
+    pub fn track(&self, id: ThreadId) {
+        self.slots.borrow_mut().entry(id).or_default();
+    }
+
+    /**
+        Returns whether the given thread is currently tracked.
+    */
+    pub(crate) fn is_tracked(&self, id: ThreadId) -> bool {
+        self.slots.borrow().contains_key(&id)
+    }
+
+    /**
+        Gets the current status of the given thread, or [`None`] if it is not tracked.
+    */
+    pub fn status(&self, id: ThreadId) -> Option<LuaThreadStatus> {
+        let slots = self.slots.borrow();
+        let slot = slots.get(&id)?;
+        Some(match &slot.result {
+            None => LuaThreadStatus::Resumable,
+            Some(ThreadResult(Ok(_))) => LuaThreadStatus::Unresumable,
+            Some(ThreadResult(Err(_))) => LuaThreadStatus::Error,
+        })
+    }
+
+    /**
+        Pushes an intermediate `coroutine.yield`-ed value for the given thread, to
+        be consumed through [`stream`](ThreadResultMap::stream).
+
+        Does nothing if the thread is not tracked.
+    */
+    pub(crate) fn push_yield(&self, id: ThreadId, value: LuaResult<Vec<LuaRegistryKey>>) {
+        if let Some(slot) = self.slots.borrow_mut().get_mut(&id) {
+            slot.yields.push_back(value);
+        }
+        self.event.notify(usize::MAX);
+    }
+
+    /**
+        Resolves the given thread with its final result, waking up any pending
+        [`listen`](ThreadResultMap::listen) or [`stream`](ThreadResultMap::stream) calls.
+
+        Does nothing if the thread is not tracked, or if it already has a result
+        (for example, from [`cancel`](ThreadResultMap::cancel)).
+    */
+    pub(crate) fn resolve(&self, id: ThreadId, value: LuaResult<Vec<LuaRegistryKey>>) {
+        if let Some(slot) = self.slots.borrow_mut().get_mut(&id) {
+            if slot.result.is_none() {
+                slot.result = Some(ThreadResult(value));
+            }
+        }
+        self.event.notify(usize::MAX);
+    }
+
+    /**
+        Cancels a tracked thread, recording a cancellation error as its result.
+
+        Returns `true` if the thread was tracked, `false` otherwise. An
+        already-resolved thread keeps its result, and `true` is still returned.
+    */
+    pub fn cancel(&self, id: ThreadId) -> bool {
+        let mut slots = self.slots.borrow_mut();
+        let Some(slot) = slots.get_mut(&id) else {
+            return false;
+        };
+        if slot.result.is_none() {
+            slot.result = Some(ThreadResult::cancelled());
+        }
+        drop(slots);
+        self.event.notify(usize::MAX);
+        true
+    }
+
+    /**
+        Times out a tracked thread, recording a timeout error as its result.
+
+        Identical to [`cancel`](ThreadResultMap::cancel), except the recorded
+        error is distinguishable from an explicit cancellation - see
+        [`ThreadResult::timed_out`].
+
+        Returns `true` if the thread was tracked, `false` otherwise. An
+        already-resolved thread keeps its result, and `true` is still returned.
+    */
+    pub fn timeout(&self, id: ThreadId) -> bool {
+        let mut slots = self.slots.borrow_mut();
+        let Some(slot) = slots.get_mut(&id) else {
+            return false;
+        };
+        if slot.result.is_none() {
+            slot.result = Some(ThreadResult::timed_out());
+        }
+        drop(slots);
+        self.event.notify(usize::MAX);
+        true
+    }
+
+    /**
+        Removes and returns the final result of the given thread, if it has resolved.
+    */
+    pub fn remove(&self, id: ThreadId) -> Option<ThreadResult> {
+        let mut slots = self.slots.borrow_mut();
+        let result = slots.get_mut(&id)?.result.take()?;
+        slots.remove(&id);
+        Some(result)
+    }
+
+    /**
+        Waits for the given thread to resolve, be it with a value or an error.
+
+        Resolves immediately if the thread is not tracked, matching the behavior
+        of waiting on a thread that has already been removed from the map.
+    */
+    pub async fn listen(&self, id: ThreadId) {
+        loop {
+            if !self.is_tracked(id)
+                || self.slots.borrow().get(&id).is_some_and(|s| s.result.is_some())
+            {
+                return;
+            }
+            let listener = self.event.listen();
+            if !self.is_tracked(id)
+                || self.slots.borrow().get(&id).is_some_and(|s| s.result.is_some())
+            {
+                return;
+            }
+            listener.await;
+        }
+    }
+
+    /**
+        Streams every intermediate `coroutine.yield`-ed value of a tracked thread,
+        ending once the thread reaches its `Unresumable` or `Error` status.
+    */
+    pub fn stream<'lua>(
+        &self,
+        lua: &'lua Lua,
+        id: ThreadId,
+    ) -> impl Stream<Item = LuaResult<LuaMultiValue<'lua>>> {
+        let map = self.clone();
+        stream::unfold((), move |()| {
+            let map = map.clone();
+            async move {
+                loop {
+                    let next = map
+                        .slots
+                        .borrow_mut()
+                        .get_mut(&id)
+                        .and_then(|slot| slot.yields.pop_front());
+                    if let Some(keys) = next {
+                        let value = keys.and_then(|keys| {
+                            let mut values = Vec::with_capacity(keys.len());
+                            for key in keys {
+                                values.push(lua.registry_value(&key)?);
+                                lua.remove_registry_value(key).ok();
+                            }
+                            Ok(LuaMultiValue::from_vec(values))
+                        });
+                        return Some((value, ()));
+                    }
+
+                    let finished = !map.is_tracked(id)
+                        || map
+                            .slots
+                            .borrow()
+                            .get(&id)
+                            .is_some_and(|s| s.result.is_some());
+                    if finished {
+                        return None;
+                    }
+
+                    map.event.listen().await;
+                }
+            }
+        })
+    }
+}
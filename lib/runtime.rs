@@ -0,0 +1,262 @@
+use std::{
+    future::Future,
+    rc::Rc,
+    sync::{Arc, Weak as WeakArc},
+    task::Poll,
+};
+
+use async_executor::{Executor, StaticExecutor};
+use futures_lite::future::poll_fn;
+use mlua::prelude::*;
+
+use crate::{
+    exit::Exit,
+    queue::{
+        DeferredThreadQueue, FuturesQueue, QueuedThread, RequeuedThreadQueue, SpawnedThreadQueue,
+    },
+    result_map::ThreadResultMap,
+    thread_id::ThreadId,
+    traits::IntoLuaThread,
+};
+
+enum ExecutorHandle {
+    Owned(Arc<Executor<'static>>),
+    Static(&'static StaticExecutor<'static>),
+}
+
+impl ExecutorHandle {
+    fn try_tick(&self) -> bool {
+        match self {
+            Self::Owned(exec) => exec.try_tick(),
+            Self::Static(exec) => exec.try_tick(),
+        }
+    }
+}
+
+/**
+    A runtime for running and scheduling lua threads, backed by an
+    [`async_executor::Executor`].
+
+    Construct with [`Runtime::new`] (or [`Runtime::new_static`] for a leaked,
+    lower-overhead executor), then drive it to completion with [`Runtime::run`]
+    or [`Runtime::run_blocking`].
+*/
+pub struct Runtime<'lua> {
+    lua: &'lua Lua,
+    exit: Exit,
+    spawned: SpawnedThreadQueue,
+    deferred: DeferredThreadQueue,
+    requeued: RequeuedThreadQueue,
+    result_map: ThreadResultMap,
+    futures: Rc<FuturesQueue>,
+    executor: ExecutorHandle,
+}
+
+impl<'lua> Runtime<'lua> {
+    /**
+        Creates a new [`Runtime`] for the given [`Lua`] instance.
+
+        # Errors
+
+        Errors when out of memory.
+    */
+    pub fn new(lua: &'lua Lua) -> LuaResult<Self> {
+        let executor = Arc::new(Executor::new());
+        lua.set_app_data(Arc::downgrade(&executor) as WeakArc<Executor<'static>>);
+        Self::new_with_executor(lua, ExecutorHandle::Owned(executor))
+    }
+
+    /**
+        Creates a new [`Runtime`] whose executor is leaked into a
+        [`StaticExecutor`] (see [`Executor::leak`]), skipping the per-spawn
+        `active` task-tracking overhead that the regular [`Executor`] pays.
+
+        The leaked executor is stored in [`Lua`] app data, so
+        [`LuaSpawnExt::spawn`] and [`LuaSpawnExt::spawn_blocking`] dispatch to it
+        instead of the regular [`WeakArc<Executor>`](WeakArc), and this
+        [`Runtime`]'s own [`run`](Runtime::run) loop drives that same executor.
+
+        Prefer this for long-lived embeddings (a game host, a server) where the
+        runtime lives for the entire process and spawn throughput matters. The
+        leaked executor, and anything ever spawned onto it, is never freed.
+
+        [`LuaSpawnExt::spawn`]: crate::LuaSpawnExt::spawn
+        [`LuaSpawnExt::spawn_blocking`]: crate::LuaSpawnExt::spawn_blocking
+
+        # Errors
+
+        Errors when out of memory.
+    */
+    pub fn new_static(lua: &'lua Lua) -> LuaResult<Self> {
+        let static_executor: &'static StaticExecutor<'static> =
+            Box::leak(Box::new(Executor::new().leak()));
+        lua.set_app_data(static_executor);
+        Self::new_with_executor(lua, ExecutorHandle::Static(static_executor))
+    }
+
+    fn new_with_executor(lua: &'lua Lua, executor: ExecutorHandle) -> LuaResult<Self> {
+        let exit = Exit::default();
+        let spawned = SpawnedThreadQueue::default();
+        let deferred = DeferredThreadQueue::default();
+        let requeued = RequeuedThreadQueue::default();
+        let result_map = ThreadResultMap::default();
+        let futures = Rc::new(FuturesQueue::default());
+
+        lua.set_app_data(exit.clone());
+        lua.set_app_data(spawned.clone());
+        lua.set_app_data(deferred.clone());
+        lua.set_app_data(requeued.clone());
+        lua.set_app_data(result_map.clone());
+        lua.set_app_data(Rc::downgrade(&futures));
+
+        Ok(Self {
+            lua,
+            exit,
+            spawned,
+            deferred,
+            requeued,
+            result_map,
+            futures,
+            executor,
+        })
+    }
+
+    /**
+        Pushes (spawns) a lua thread onto the runtime's spawn queue, to be
+        resumed before any deferred thread. Spawned threads are resumed in the
+        order they were pushed.
+
+        # Errors
+
+        Errors when out of memory.
+    */
+    pub fn push_thread_front(
+        &self,
+        thread: impl IntoLuaThread<'lua>,
+        args: impl IntoLuaMulti<'lua>,
+    ) -> LuaResult<ThreadId> {
+        self.spawned.push_item(self.lua, thread, args)
+    }
+
+    /**
+        Pushes (defers) a lua thread to the **back** of the runtime's queue, to be
+        resumed after all currently spawned threads have been resumed at least once.
+
+        # Errors
+
+        Errors when out of memory.
+    */
+    pub fn push_thread_back(
+        &self,
+        thread: impl IntoLuaThread<'lua>,
+        args: impl IntoLuaMulti<'lua>,
+    ) -> LuaResult<ThreadId> {
+        self.deferred.push_item(self.lua, thread, args)
+    }
+
+    /**
+        Sets the exit code of this runtime, making [`run`](Runtime::run) stop at
+        the end of its current tick. [`run`](Runtime::run) does not return the code.
+    */
+    pub fn set_exit_code(&self, code: std::process::ExitCode) {
+        self.exit.set(code);
+    }
+
+    /**
+        Runs the runtime until all spawned threads and tasks have completed.
+    */
+    pub async fn run(&self) {
+        loop {
+            // Threads that yielded last tick are merged back in here, rather
+            // than being re-fed directly into `spawned`, so a thread that
+            // keeps yielding can't starve every other spawned thread, task,
+            // and the executor within a single tick.
+            while let Some(queued) = self.requeued.pop_item() {
+                self.spawned.push_requeue(queued);
+            }
+
+            while let Some(queued) = self.spawned.pop_item() {
+                self.resume_thread(queued);
+            }
+            while let Some(queued) = self.deferred.pop_item() {
+                self.resume_thread(queued);
+            }
+
+            let executor_progress = self.executor.try_tick();
+            let futures_progress = poll_fn(|cx| Poll::Ready(self.futures.drain_polls(cx))).await;
+
+            let idle = self.spawned.is_empty()
+                && self.deferred.is_empty()
+                && self.requeued.is_empty()
+                && self.futures.is_empty()
+                && !executor_progress
+                && !futures_progress;
+
+            if idle || self.exit.get().is_some() {
+                break;
+            }
+        }
+    }
+
+    /**
+        Runs the runtime until all spawned threads and tasks have completed,
+        blocking the current thread.
+    */
+    pub fn run_blocking(&self) {
+        async_io::block_on(self.run());
+    }
+
+    /**
+        Resumes a single queued thread once, pushing its yielded value onto the
+        [`ThreadResultMap`] and requeuing it onto [`RequeuedThreadQueue`] if it's
+        still resumable afterwards, or resolving it with its final value/error
+        otherwise. A thread that was cancelled or timed out while it was running
+        is not requeued.
+    */
+    fn resume_thread(&self, queued: QueuedThread) {
+        let QueuedThread { id, key, args } = queued;
+
+        let thread: LuaThread = match self.lua.registry_value(&key) {
+            Ok(thread) => thread,
+            Err(err) => {
+                self.result_map.resolve(id, Err(err));
+                return;
+            }
+        };
+        self.lua.remove_registry_value(key).ok();
+
+        let mut resume_args = LuaMultiValue::new();
+        for arg_key in args {
+            if let Ok(value) = self.lua.registry_value(&arg_key) {
+                resume_args.push_back(value);
+            }
+            self.lua.remove_registry_value(arg_key).ok();
+        }
+
+        match thread.resume::<_, LuaMultiValue>(resume_args) {
+            Err(err) => self.result_map.resolve(id, Err(err)),
+            Ok(values) => {
+                let keys = values
+                    .into_iter()
+                    .map(|value| self.lua.create_registry_value(value))
+                    .collect::<LuaResult<Vec<_>>>();
+
+                if thread.status() == LuaThreadStatus::Resumable {
+                    if matches!(self.result_map.status(id), Some(LuaThreadStatus::Error)) {
+                        return;
+                    }
+                    self.result_map.push_yield(id, keys);
+                    if let Ok(key) = self.lua.create_registry_value(thread) {
+                        self.requeued.push_requeue(QueuedThread {
+                            id,
+                            key,
+                            args: Vec::new(),
+                        });
+                    }
+                } else {
+                    self.result_map.resolve(id, keys);
+                }
+            }
+        }
+    }
+}
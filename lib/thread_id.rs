@@ -0,0 +1,20 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_THREAD_ID: AtomicUsize = AtomicUsize::new(0);
+
+/**
+    A unique, opaque identifier for a lua thread that has been pushed onto a
+    [`Runtime`](crate::Runtime).
+
+    Obtained from [`LuaRuntimeExt::push_thread_front`](crate::LuaRuntimeExt::push_thread_front)
+    or [`LuaRuntimeExt::push_thread_back`](crate::LuaRuntimeExt::push_thread_back), and used to
+    track, cancel, or retrieve the result of that thread.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ThreadId(usize);
+
+impl ThreadId {
+    pub(crate) fn next() -> Self {
+        Self(NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
@@ -2,16 +2,19 @@
 #![allow(clippy::missing_errors_doc)]
 
 use std::{
-    cell::Cell, future::Future, process::ExitCode, rc::Weak as WeakRc, sync::Weak as WeakArc,
+    cell::Cell, future::Future, process::ExitCode, rc::Rc, rc::Weak as WeakRc,
+    sync::Weak as WeakArc, time::Duration,
 };
 
-use async_executor::{Executor, Task};
+use async_executor::{Executor, StaticExecutor, Task};
+use async_io::Timer;
+use futures_lite::{FutureExt, Stream};
 use mlua::prelude::*;
 use tracing::trace;
 
 use crate::{
     exit::Exit,
-    queue::{DeferredThreadQueue, FuturesQueue, SpawnedThreadQueue},
+    queue::{DeferredThreadQueue, FuturesQueue, RequeuedThreadQueue, SpawnedThreadQueue},
     result_map::ThreadResultMap,
     runtime::Runtime,
     thread_id::ThreadId,
@@ -142,6 +145,117 @@ pub trait LuaRuntimeExt<'lua> {
         Panics if called outside of a running [`Runtime`].
     */
     fn wait_for_thread(&'lua self, id: ThreadId) -> impl Future<Output = ()>;
+
+    /**
+        Gets the current status of the given thread.
+
+        Returns [`None`] if the thread is not being tracked by the current runtime,
+        for example if it was never passed to [`track_thread`] or if its result has
+        already been retrieved using [`get_thread_result`].
+
+        [`track_thread`]: LuaRuntimeExt::track_thread
+        [`get_thread_result`]: LuaRuntimeExt::get_thread_result
+
+        # Panics
+
+        Panics if called outside of a running [`Runtime`].
+    */
+    fn thread_status(&'lua self, id: ThreadId) -> Option<LuaThreadStatus>;
+
+    /**
+        Cancels a tracked thread, preventing it from ever being resumed.
+
+        If the thread has not yet started running it is removed from the runtime's
+        spawn / defer queues. Either way, unless the thread has already finished,
+        its entry in the result map is resolved with a cancellation error, so that
+        a pending or future call to [`wait_for_thread`] wakes up and
+        [`get_thread_result`] returns [`LuaError::RuntimeError`] instead of hanging
+        forever. A thread that has already finished keeps its own result.
+
+        Returns `true` if the thread was tracked (including one that has already
+        finished), `false` if it was not tracked by this runtime.
+
+        [`wait_for_thread`]: LuaRuntimeExt::wait_for_thread
+        [`get_thread_result`]: LuaRuntimeExt::get_thread_result
+
+        # Panics
+
+        Panics if called outside of a running [`Runtime`].
+    */
+    fn cancel_thread(&'lua self, id: ThreadId) -> bool;
+
+    /**
+        Pushes a lua thread to the front of the current runtime, tracks it, and
+        waits for it to complete, resolving to its result.
+
+        This combines [`push_thread_front`], [`track_thread`], [`wait_for_thread`],
+        and [`get_thread_result`] into a single call, without the need to keep
+        track of the thread's [`ThreadId`] in the caller.
+
+        [`push_thread_front`]: LuaRuntimeExt::push_thread_front
+        [`track_thread`]: LuaRuntimeExt::track_thread
+        [`wait_for_thread`]: LuaRuntimeExt::wait_for_thread
+        [`get_thread_result`]: LuaRuntimeExt::get_thread_result
+
+        # Panics
+
+        Panics if called outside of a running [`Runtime`].
+    */
+    fn await_thread(
+        &'lua self,
+        thread: impl IntoLuaThread<'lua>,
+        args: impl IntoLuaMulti<'lua>,
+    ) -> impl Future<Output = LuaResult<LuaMultiValue<'lua>>>;
+
+    /**
+        Streams the values yielded by a tracked thread.
+
+        Unlike [`wait_for_thread`] / [`get_thread_result`], which only ever
+        surface a thread's final return value, this yields once per
+        `coroutine.yield` call made by the thread, and ends once the thread
+        reaches its `Unresumable` or `Error` status.
+
+        The given thread must already be tracked, see [`track_thread`].
+
+        [`track_thread`]: LuaRuntimeExt::track_thread
+        [`wait_for_thread`]: LuaRuntimeExt::wait_for_thread
+        [`get_thread_result`]: LuaRuntimeExt::get_thread_result
+
+        # Panics
+
+        Panics if called outside of a running [`Runtime`].
+    */
+    fn stream_thread(
+        &'lua self,
+        id: ThreadId,
+    ) -> impl Stream<Item = LuaResult<LuaMultiValue<'lua>>>;
+
+    /**
+        Waits for the given thread to complete, or times it out if the given
+        `timeout` elapses first.
+
+        Returns `true` if the thread completed before the timeout elapsed, `false`
+        if it timed out first. In the latter case, a subsequent [`get_thread_result`]
+        returns a timeout error - distinguishable from the error left behind by an
+        explicit [`cancel_thread`] - instead of hanging forever.
+
+        This is a safety valve against runaway Luau coroutines - for example, a
+        host function can [`push_thread_front`] a thread and then race it against
+        this method instead of waiting on it unconditionally.
+
+        [`push_thread_front`]: LuaRuntimeExt::push_thread_front
+        [`cancel_thread`]: LuaRuntimeExt::cancel_thread
+        [`get_thread_result`]: LuaRuntimeExt::get_thread_result
+
+        # Panics
+
+        Panics if called outside of a running [`Runtime`].
+    */
+    fn wait_for_thread_timeout(
+        &'lua self,
+        id: ThreadId,
+        timeout: Duration,
+    ) -> impl Future<Output = bool>;
 }
 
 /**
@@ -198,10 +312,13 @@ pub trait LuaSpawnExt<'lua> {
         T: Send + 'static;
 
     /**
-        Spawns the given thread-local future on the current executor.
+        Spawns the given thread-local future on the current executor, returning
+        a [`LocalTask`] handle.
 
-        Note that this future will run detached and always to completion,
-        preventing the [`Runtime`] was spawned on from completing until done.
+        Note that the returned [`LocalTask`] cancels the future if dropped -
+        call [`LocalTask::detach`] to let it run, detached, to completion
+        instead, preventing the [`Runtime`] it was spawned on from completing
+        until done.
 
         # Panics
 
@@ -223,7 +340,7 @@ pub trait LuaSpawnExt<'lua> {
                 lua.create_async_function(|lua, ()| async move {
                     lua.spawn_local(async move {
                         println!("Hello from local task!");
-                    });
+                    }).detach();
                     Ok(())
                 })?
             )?;
@@ -236,7 +353,7 @@ pub trait LuaSpawnExt<'lua> {
         }
         ```
     */
-    fn spawn_local<F>(&self, fut: F)
+    fn spawn_local<F>(&self, fut: F) -> LocalTask
     where
         F: Future<Output = ()> + 'static;
 
@@ -284,6 +401,34 @@ pub trait LuaSpawnExt<'lua> {
         T: Send + 'static;
 }
 
+/**
+    A handle to a thread-local future spawned with [`LuaSpawnExt::spawn_local`].
+
+    Dropping this handle cancels the spawned future, stopping it from ever
+    being polled again - call [`detach`](LocalTask::detach) to let it run to
+    completion instead.
+*/
+#[must_use = "dropping this cancels the spawned task; call `.detach()` to run it to completion"]
+pub struct LocalTask {
+    cancelled: Rc<Cell<bool>>,
+}
+
+impl LocalTask {
+    /**
+        Detaches the task, letting it run to completion in the background
+        instead of being cancelled when this handle is dropped.
+    */
+    pub fn detach(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for LocalTask {
+    fn drop(&mut self) {
+        self.cancelled.set(true);
+    }
+}
+
 impl<'lua> LuaRuntimeExt<'lua> for Lua {
     fn set_exit_code(&self, code: ExitCode) {
         let exit = self
@@ -334,6 +479,113 @@ impl<'lua> LuaRuntimeExt<'lua> for Lua {
             .expect("lua threads results can only be retrieved within a runtime");
         async move { map.listen(id).await }
     }
+
+    fn thread_status(&'lua self, id: ThreadId) -> Option<LuaThreadStatus> {
+        let map = self
+            .app_data_ref::<ThreadResultMap>()
+            .expect("lua thread status can only be retrieved within a runtime");
+        map.status(id)
+    }
+
+    fn cancel_thread(&'lua self, id: ThreadId) -> bool {
+        let map = self
+            .app_data_ref::<ThreadResultMap>()
+            .expect("lua threads can only be cancelled within a runtime");
+
+        // Only tracked threads are eligible for cancellation - this is the same
+        // precondition as `wait_for_thread` / `get_thread_result`, and it's what
+        // lets us give a straight answer to "was this thread cancelled?" instead
+        // of quietly cancelling a queued-but-untracked thread while leaving no
+        // record of it, which would otherwise hang a later `track_thread` +
+        // `wait_for_thread` on the same id forever.
+        if !map.cancel(id) {
+            return false;
+        }
+
+        let spawned = self
+            .app_data_ref::<SpawnedThreadQueue>()
+            .expect("lua threads can only be cancelled within a runtime");
+        let deferred = self
+            .app_data_ref::<DeferredThreadQueue>()
+            .expect("lua threads can only be cancelled within a runtime");
+        let requeued = self
+            .app_data_ref::<RequeuedThreadQueue>()
+            .expect("lua threads can only be cancelled within a runtime");
+
+        // Best-effort: also drop the thread from whichever queue it's still
+        // sitting in - spawned, deferred, or requeued after a yield - so it is
+        // never resumed again. A thread that is running right now is in none of
+        // them - the cancellation sentinel recorded above is what stops it from
+        // being observed as completing normally, or from being requeued.
+        spawned.cancel_item(id);
+        deferred.cancel_item(id);
+        requeued.cancel_item(id);
+
+        true
+    }
+
+    fn await_thread(
+        &'lua self,
+        thread: impl IntoLuaThread<'lua>,
+        args: impl IntoLuaMulti<'lua>,
+    ) -> impl Future<Output = LuaResult<LuaMultiValue<'lua>>> {
+        async move {
+            let id = self.push_thread_front(thread, args)?;
+            self.track_thread(id);
+            self.wait_for_thread(id).await;
+            self.get_thread_result(id)
+                .expect("thread result must be present after waiting for completion")
+        }
+    }
+
+    fn stream_thread(
+        &'lua self,
+        id: ThreadId,
+    ) -> impl Stream<Item = LuaResult<LuaMultiValue<'lua>>> {
+        let map = self
+            .app_data_ref::<ThreadResultMap>()
+            .expect("lua threads can only be streamed within a runtime");
+        map.stream(self, id)
+    }
+
+    fn wait_for_thread_timeout(
+        &'lua self,
+        id: ThreadId,
+        timeout: Duration,
+    ) -> impl Future<Output = bool> {
+        async move {
+            let completed = async {
+                self.wait_for_thread(id).await;
+                true
+            };
+            let timed_out = async {
+                Timer::after(timeout).await;
+                false
+            };
+            if completed.or(timed_out).await {
+                true
+            } else {
+                let map = self
+                    .app_data_ref::<ThreadResultMap>()
+                    .expect("lua threads can only be timed out within a runtime");
+                if map.timeout(id) {
+                    let spawned = self
+                        .app_data_ref::<SpawnedThreadQueue>()
+                        .expect("lua threads can only be timed out within a runtime");
+                    let deferred = self
+                        .app_data_ref::<DeferredThreadQueue>()
+                        .expect("lua threads can only be timed out within a runtime");
+                    let requeued = self
+                        .app_data_ref::<RequeuedThreadQueue>()
+                        .expect("lua threads can only be timed out within a runtime");
+                    spawned.cancel_item(id);
+                    deferred.cancel_item(id);
+                    requeued.cancel_item(id);
+                }
+                false
+            }
+        }
+    }
 }
 
 impl<'lua> LuaSpawnExt<'lua> for Lua {
@@ -342,6 +594,10 @@ impl<'lua> LuaSpawnExt<'lua> for Lua {
         F: Future<Output = T> + Send + 'static,
         T: Send + 'static,
     {
+        if let Some(exec) = self.app_data_ref::<&'static StaticExecutor<'static>>() {
+            trace!("spawning future on static executor");
+            return exec.spawn(fut);
+        }
         let exec = self
             .app_data_ref::<WeakArc<Executor>>()
             .expect("tasks can only be spawned within a runtime")
@@ -351,7 +607,7 @@ impl<'lua> LuaSpawnExt<'lua> for Lua {
         exec.spawn(fut)
     }
 
-    fn spawn_local<F>(&self, fut: F)
+    fn spawn_local<F>(&self, fut: F) -> LocalTask
     where
         F: Future<Output = ()> + 'static,
     {
@@ -361,7 +617,8 @@ impl<'lua> LuaSpawnExt<'lua> for Lua {
             .upgrade()
             .expect("executor was dropped");
         trace!("spawning local task on executor");
-        queue.push_item(fut);
+        let cancelled = queue.push_item(fut);
+        LocalTask { cancelled }
     }
 
     fn spawn_blocking<F, T>(&self, f: F) -> Task<T>
@@ -369,6 +626,10 @@ impl<'lua> LuaSpawnExt<'lua> for Lua {
         F: FnOnce() -> T + Send + 'static,
         T: Send + 'static,
     {
+        if let Some(exec) = self.app_data_ref::<&'static StaticExecutor<'static>>() {
+            trace!("spawning blocking task on static executor");
+            return exec.spawn(blocking::unblock(f));
+        }
         let exec = self
             .app_data_ref::<WeakArc<Executor>>()
             .expect("tasks can only be spawned within a runtime")